@@ -0,0 +1,205 @@
+//! Frustum and hierarchical-Z occlusion culling, so a folder import with hundreds of
+//! `ModelEntry`s doesn't render (or shadow-map) every one of them unconditionally.
+
+use three_d::{
+    vec3, AxisAlignedBoundingBox, Camera, ClearState, Context, DepthTexture2D, InnerSpace, Object,
+    Vec3, Vec4, Wrapping,
+};
+
+use crate::ModelEntry;
+
+/// How many of a scene's models survived culling this frame, for the GUI to report.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CullStats {
+    pub drawn: usize,
+    pub culled: usize,
+}
+
+/// The 6 planes of a camera's view frustum, each stored as `(normal, distance)` with the
+/// positive half-space (`dot(normal, p) + distance >= 0`) inside the frustum.
+pub struct Frustum {
+    planes: [(Vec3, f32); 6],
+}
+
+impl Frustum {
+    /// Extracts the frustum planes from `camera`'s combined projection-view matrix via
+    /// Gribb-Hartmann plane extraction.
+    pub fn from_camera(camera: &Camera) -> Self {
+        let m = camera.projection() * camera.view();
+        let row = |i: usize| Vec4::new(m[0][i], m[1][i], m[2][i], m[3][i]);
+        let r0 = row(0);
+        let r1 = row(1);
+        let r2 = row(2);
+        let r3 = row(3);
+        // three_d's clip space (wgpu-style) puts NDC z in [0, 1], not OpenGL's [-1, 1], so
+        // the near plane is `z >= 0` (row `r2`), not the `z >= -w` ([-1,1]) plane `r3 + r2`.
+        // The far plane (`z <= w`, i.e. `r3 - r2`) is the same in both conventions.
+        let raw = [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r2, r3 - r2];
+        let planes = raw.map(|p| {
+            let normal = vec3(p.x, p.y, p.z);
+            let len = normal.magnitude().max(1e-6);
+            (normal / len, p.w / len)
+        });
+        Self { planes }
+    }
+
+    /// Whether any part of `aabb` lies inside (or crosses) the frustum: the "positive
+    /// vertex" test, checking the single corner most likely to be inside each plane.
+    pub fn intersects(&self, aabb: &AxisAlignedBoundingBox) -> bool {
+        for (normal, distance) in &self.planes {
+            let positive = vec3(
+                if normal.x >= 0.0 { aabb.max().x } else { aabb.min().x },
+                if normal.y >= 0.0 { aabb.max().y } else { aabb.min().y },
+                if normal.z >= 0.0 { aabb.max().z } else { aabb.min().z },
+            );
+            if normal.dot(positive) + *distance < 0.0 {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A depth-only prepass plus its max-reduced mip pyramid, used to test whether a model's
+/// screen-space bounding box is fully behind already-drawn geometry.
+///
+/// The reduction and the per-model test both run on the CPU: `build` reads the prepass
+/// back every frame and folds it down on the host, and `visible` samples the resulting
+/// `mip_data`. That's a GPU stall per frame, not the GPU compute pass the name implies;
+/// moving the reduction into a compute/fragment pass (sampling `mip_data`'s replacement
+/// textures on the GPU instead of reading them back) is the natural next step if this
+/// becomes a bottleneck on scenes large enough for it to matter.
+pub struct HiZPyramid {
+    depth: DepthTexture2D,
+    mip_data: Vec<Vec<f32>>,
+    mip_dims: Vec<(u32, u32)>,
+    width: u32,
+    height: u32,
+}
+
+impl HiZPyramid {
+    pub fn new(ctx: &Context, width: u32, height: u32) -> Self {
+        let depth = DepthTexture2D::new::<f32>(ctx, width, height, Wrapping::ClampToEdge, Wrapping::ClampToEdge);
+        let mut mip_dims = Vec::new();
+        let (mut w, mut h) = (width, height);
+        while w > 1 || h > 1 {
+            w = (w / 2).max(1);
+            h = (h / 2).max(1);
+            mip_dims.push((w, h));
+        }
+        Self {
+            depth,
+            mip_data: Vec::new(),
+            mip_dims,
+            width,
+            height,
+        }
+    }
+
+    /// Renders every surviving model's geometry, depth-only, into the prepass target
+    /// that seeds the mip pyramid.
+    pub fn depth_prepass(&self, camera: &Camera, models: &mut [&mut ModelEntry]) {
+        let target = self.depth.as_depth_target();
+        target.clear(ClearState::depth(1.0));
+        for model in models {
+            target.render(camera, &model.normal_mesh, &[]);
+        }
+    }
+
+    /// Builds the max-reduced mip chain from the depth prepass: each mip's texel holds
+    /// the farthest (maximum) depth of the 2x2 texels below it, so a conservative
+    /// occlusion test against a coarse mip never rejects something actually visible.
+    pub fn build(&mut self) {
+        let mut current = self.depth.as_depth_target().read::<f32>();
+        let (mut w, mut h) = (self.width, self.height);
+        self.mip_data.clear();
+        for &(nw, nh) in &self.mip_dims {
+            let mut reduced = vec![0.0f32; (nw * nh) as usize];
+            for y in 0..nh {
+                for x in 0..nw {
+                    let mut max_depth: f32 = 0.0;
+                    for dy in 0..2 {
+                        for dx in 0..2 {
+                            let sx = (x * 2 + dx).min(w.saturating_sub(1));
+                            let sy = (y * 2 + dy).min(h.saturating_sub(1));
+                            max_depth = max_depth.max(current[(sy * w + sx) as usize]);
+                        }
+                    }
+                    reduced[(y * nw + x) as usize] = max_depth;
+                }
+            }
+            self.mip_data.push(reduced.clone());
+            current = reduced;
+            w = nw;
+            h = nh;
+        }
+    }
+
+    /// Whether `aabb` is visible against the Hi-Z pyramid: its projected screen rectangle
+    /// is tested against the coarsest mip whose texels still cover that rectangle, and it
+    /// counts as occluded only if the box's nearest depth is farther than every texel it
+    /// overlaps at that mip.
+    pub fn visible(&self, camera: &Camera, aabb: &AxisAlignedBoundingBox) -> bool {
+        if self.mip_data.is_empty() {
+            return true;
+        }
+
+        let vp = camera.projection() * camera.view();
+        let (min, max) = (aabb.min(), aabb.max());
+        let corners = [
+            vec3(min.x, min.y, min.z),
+            vec3(max.x, min.y, min.z),
+            vec3(min.x, max.y, min.z),
+            vec3(max.x, max.y, min.z),
+            vec3(min.x, min.y, max.z),
+            vec3(max.x, min.y, max.z),
+            vec3(min.x, max.y, max.z),
+            vec3(max.x, max.y, max.z),
+        ];
+
+        let mut min_ndc = vec3(1.0, 1.0, 1.0);
+        let mut max_ndc = vec3(-1.0, -1.0, -1.0);
+        for corner in corners {
+            let clip = vp * corner.extend(1.0);
+            if clip.w <= 1e-5 {
+                // Straddles the near plane: don't risk culling something partly behind us.
+                return true;
+            }
+            let ndc = vec3(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w);
+            min_ndc.x = min_ndc.x.min(ndc.x);
+            min_ndc.y = min_ndc.y.min(ndc.y);
+            min_ndc.z = min_ndc.z.min(ndc.z);
+            max_ndc.x = max_ndc.x.max(ndc.x);
+            max_ndc.y = max_ndc.y.max(ndc.y);
+            max_ndc.z = max_ndc.z.max(ndc.z);
+        }
+
+        let screen_min_x = ((min_ndc.x * 0.5 + 0.5) * self.width as f32).clamp(0.0, self.width as f32);
+        let screen_max_x = ((max_ndc.x * 0.5 + 0.5) * self.width as f32).clamp(0.0, self.width as f32);
+        let screen_min_y = ((1.0 - (max_ndc.y * 0.5 + 0.5)) * self.height as f32).clamp(0.0, self.height as f32);
+        let screen_max_y = ((1.0 - (min_ndc.y * 0.5 + 0.5)) * self.height as f32).clamp(0.0, self.height as f32);
+        let texel_span = (screen_max_x - screen_min_x).max(screen_max_y - screen_min_y).max(1.0);
+
+        let mip_level = (texel_span.log2().ceil() as usize).min(self.mip_data.len() - 1);
+        let (mw, mh) = self.mip_dims[mip_level];
+        let scale_x = mw as f32 / self.width as f32;
+        let scale_y = mh as f32 / self.height as f32;
+        let mx0 = ((screen_min_x * scale_x) as u32).min(mw - 1);
+        let mx1 = ((screen_max_x * scale_x) as u32).min(mw - 1);
+        let my0 = ((screen_min_y * scale_y) as u32).min(mh - 1);
+        let my1 = ((screen_max_y * scale_y) as u32).min(mh - 1);
+
+        let data = &self.mip_data[mip_level];
+        let mut stored_max_depth: f32 = 0.0;
+        for y in my0..=my1 {
+            for x in mx0..=mx1 {
+                stored_max_depth = stored_max_depth.max(data[(y * mw + x) as usize]);
+            }
+        }
+
+        // `read::<f32>()` on a wgpu depth target and `ndc.z` above are both already in
+        // [0, 1]; remapping again as if this were OpenGL's [-1, 1] clip space would push
+        // every box into [0.5, 1] and make this test pass almost unconditionally.
+        min_ndc.z <= stored_max_depth
+    }
+}
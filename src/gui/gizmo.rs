@@ -0,0 +1,192 @@
+use three_d::egui;
+use three_d::{vec3, vec4, Camera, Mat4, Rad, Vec3};
+
+/// Which operation the gizmo currently performs on the selected model's transform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoMode {
+    Translate,
+    Rotate,
+    Scale,
+}
+
+/// Which of the three world axes a drag is constrained to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl GizmoAxis {
+    const ALL: [GizmoAxis; 3] = [GizmoAxis::X, GizmoAxis::Y, GizmoAxis::Z];
+
+    fn direction(self) -> Vec3 {
+        match self {
+            GizmoAxis::X => vec3(1.0, 0.0, 0.0),
+            GizmoAxis::Y => vec3(0.0, 1.0, 0.0),
+            GizmoAxis::Z => vec3(0.0, 0.0, 1.0),
+        }
+    }
+
+    fn color(self) -> egui::Color32 {
+        match self {
+            GizmoAxis::X => egui::Color32::from_rgb(220, 60, 60),
+            GizmoAxis::Y => egui::Color32::from_rgb(60, 220, 60),
+            GizmoAxis::Z => egui::Color32::from_rgb(60, 60, 220),
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            GizmoAxis::X => 0,
+            GizmoAxis::Y => 1,
+            GizmoAxis::Z => 2,
+        }
+    }
+}
+
+const HANDLE_LENGTH: f32 = 1.0;
+const HANDLE_PICK_RADIUS: f32 = 8.0;
+const TRANSLATE_SENSITIVITY: f32 = 0.01;
+const ROTATE_SENSITIVITY: f32 = 0.01;
+const SCALE_SENSITIVITY: f32 = 0.005;
+
+/// A translate/rotate/scale manipulator drawn over the viewport for the selected model.
+///
+/// It only reads the camera and the model's current transform; the caller converts the
+/// returned world transform back into the node's local transform, and
+/// `Resources::update_world_transforms` pushes it into the model through
+/// `ModelEntry::set_transformation`. The handles are drawn at
+/// `transform`, which the caller computes as `parent_world * local_transform` — this
+/// only lines up with the rendered mesh once `Resources::update_world_transforms` has
+/// pushed that same world transform into the mesh, so a freshly loaded (unedited) node
+/// renders in the right place from the first frame instead of only after its first drag.
+pub struct Gizmo {
+    pub mode: GizmoMode,
+    active_axis: Option<GizmoAxis>,
+    drag_anchor: Option<egui::Pos2>,
+}
+
+impl Default for Gizmo {
+    fn default() -> Self {
+        Self {
+            mode: GizmoMode::Translate,
+            active_axis: None,
+            drag_anchor: None,
+        }
+    }
+}
+
+impl Gizmo {
+    /// Draws the mode toolbar and the axis handles, applies any in-progress drag, and
+    /// returns the model's new world transform if the drag changed it this frame.
+    pub fn show(&mut self, ui: &mut egui::Ui, camera: &Camera, transform: Mat4) -> Option<Mat4> {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.mode, GizmoMode::Translate, "Move");
+            ui.selectable_value(&mut self.mode, GizmoMode::Rotate, "Rotate");
+            ui.selectable_value(&mut self.mode, GizmoMode::Scale, "Scale");
+        });
+
+        let origin = transform.w.truncate();
+        let origin_screen = project(camera, origin)?;
+
+        let painter = ui.ctx().layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            egui::Id::new("model_gizmo"),
+        ));
+        let pointer = ui.ctx().input(|i| i.pointer.clone());
+
+        let mut axis_screen_dirs = [egui::Vec2::ZERO; 3];
+        for axis in GizmoAxis::ALL {
+            let Some(tip_screen) = project(camera, origin + axis.direction() * HANDLE_LENGTH)
+            else {
+                continue;
+            };
+            axis_screen_dirs[axis.index()] = tip_screen - origin_screen;
+
+            painter.line_segment([origin_screen, tip_screen], (2.0, axis.color()));
+
+            if let Some(pos) = pointer.interact_pos() {
+                if pointer.primary_pressed()
+                    && distance_to_segment(pos, origin_screen, tip_screen) < HANDLE_PICK_RADIUS
+                {
+                    self.active_axis = Some(axis);
+                    self.drag_anchor = Some(pos);
+                }
+            }
+        }
+
+        if pointer.primary_released() {
+            self.active_axis = None;
+            self.drag_anchor = None;
+        }
+
+        let (axis, anchor) = (self.active_axis?, self.drag_anchor?);
+        let pos = pointer.interact_pos()?;
+        let axis_screen_dir = axis_screen_dirs[axis.index()];
+        let axis_len = axis_screen_dir.length().max(1.0);
+        let along_axis = dot(pos - anchor, axis_screen_dir) / axis_len;
+        self.drag_anchor = Some(pos);
+
+        // Left-multiplying a translation delta moves the model along the world axis,
+        // so `delta * transform` is used as-is. Rotate/Scale deltas have no translation of their own, but
+        // left-multiplying them straight onto `transform` also spins/grows its
+        // translation column — pivoting the model around the world origin instead of
+        // around its own. Bracket those deltas between `+origin`/`-origin` translations
+        // so they only touch `transform`'s linear part.
+        let new_transform = match self.mode {
+            GizmoMode::Translate => {
+                let delta =
+                    Mat4::from_translation(axis.direction() * along_axis * TRANSLATE_SENSITIVITY);
+                delta * transform
+            }
+            GizmoMode::Rotate => {
+                let delta = Mat4::from_axis_angle(axis.direction(), Rad(along_axis * ROTATE_SENSITIVITY));
+                Mat4::from_translation(origin) * delta * Mat4::from_translation(-origin) * transform
+            }
+            GizmoMode::Scale => {
+                let factor = (1.0 + along_axis * SCALE_SENSITIVITY).max(0.01);
+                let delta = scale_along_axis(axis, factor);
+                Mat4::from_translation(origin) * delta * Mat4::from_translation(-origin) * transform
+            }
+        };
+
+        Some(new_transform)
+    }
+}
+
+fn scale_along_axis(axis: GizmoAxis, factor: f32) -> Mat4 {
+    match axis {
+        GizmoAxis::X => Mat4::from_nonuniform_scale(factor, 1.0, 1.0),
+        GizmoAxis::Y => Mat4::from_nonuniform_scale(1.0, factor, 1.0),
+        GizmoAxis::Z => Mat4::from_nonuniform_scale(1.0, 1.0, factor),
+    }
+}
+
+fn dot(a: egui::Vec2, b: egui::Vec2) -> f32 {
+    a.x * b.x + a.y * b.y
+}
+
+fn distance_to_segment(p: egui::Pos2, a: egui::Pos2, b: egui::Pos2) -> f32 {
+    let ab = b - a;
+    let len2 = dot(ab, ab).max(1e-6);
+    let t = (dot(p - a, ab) / len2).clamp(0.0, 1.0);
+    let closest = a + ab * t;
+    (p - closest).length()
+}
+
+/// Projects a world-space point to screen-space pixel coordinates using the camera's
+/// view/projection matrices, returning `None` for points behind the camera.
+fn project(camera: &Camera, point: Vec3) -> Option<egui::Pos2> {
+    let clip = *camera.projection() * *camera.view() * vec4(point.x, point.y, point.z, 1.0);
+    if clip.w <= 0.0001 {
+        return None;
+    }
+    let ndc_x = clip.x / clip.w;
+    let ndc_y = clip.y / clip.w;
+
+    let viewport = camera.viewport();
+    let x = viewport.x as f32 + (ndc_x * 0.5 + 0.5) * viewport.width as f32;
+    let y = viewport.y as f32 + (1.0 - (ndc_y * 0.5 + 0.5)) * viewport.height as f32;
+    Some(egui::Pos2::new(x, y))
+}
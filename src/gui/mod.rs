@@ -1,12 +1,38 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 use three_d::egui::*;
+use three_d::{Camera, Mat4, Srgba, SquareMatrix};
 
-use crate::{load_models, Resources};
+use crate::culling::CullStats;
+use crate::procedural::{self, Grid, ScalarField};
+use crate::render_mode::MaterialType;
+use crate::shadow::{ShadowConfig, ShadowFilterMode};
+use crate::{load_models, ModelEntry, Resources, SceneNode};
+
+pub mod gizmo;
+use gizmo::Gizmo;
+
+/// Indices from a scene root down to a node: `[root_idx, child_idx, ...]`.
+pub type NodePath = Vec<usize>;
+
+/// Display names for the main loop's fixed shadow-casting lights, in the order their
+/// `ShadowConfig`s appear in `Config::shadow_configs`.
+pub const SHADOW_CASTER_NAMES: [&str; 3] = ["Directional 0", "Directional 1", "Spot 0"];
 
 pub struct Config {
     pub asset_menu: bool,
     pub showing_assets: bool,
-    pub selected_asset: Option<usize>,
+    pub selected_asset: Option<NodePath>,
+    pub procedural_menu: bool,
+    pub shadow_settings_menu: bool,
+    pub shadow_configs: [ShadowConfig; 3],
+    pub render_settings_menu: bool,
+    /// Which pipeline renders the scene (`Forward`/`DebugChannels`), or which G-buffer
+    /// channel to blit full-screen instead, while the debug-channel path is active.
+    pub material_type: MaterialType,
+    /// How many models the main loop's culling pass drew vs. skipped last frame.
+    pub cull_stats: CullStats,
 }
 
 impl Default for Config {
@@ -15,6 +41,12 @@ impl Default for Config {
             asset_menu: true,
             showing_assets: false,
             selected_asset: None,
+            procedural_menu: false,
+            shadow_settings_menu: false,
+            shadow_configs: [ShadowConfig::default(); 3],
+            render_settings_menu: false,
+            material_type: MaterialType::Forward,
+            cull_stats: CullStats::default(),
         }
     }
 }
@@ -24,7 +56,7 @@ pub trait IGui {
         "Window"
     }
 
-    fn show(&mut self, config: &mut Config, ctx: &three_d::egui::Context);
+    fn show(&mut self, config: &mut Config, camera: &Camera, ctx: &three_d::egui::Context);
 }
 
 pub struct Gui {
@@ -42,13 +74,19 @@ impl Gui {
         let mm = MainMenu {};
         let asset_menu = AssetMenu {
             resources: Arc::clone(&resources),
+            thumbnails: RefCell::new(HashMap::new()),
         };
         let asset_viewer = AssetViewer {
-            resources: Arc::clone(&resources)
+            resources: Arc::clone(&resources),
+            gizmo: Gizmo::default(),
         };
+        let procedural_menu = ProceduralMenu::new(Arc::clone(&resources));
         gui.add_ui_element(mm);
         gui.add_ui_element(asset_menu);
         gui.add_ui_element(asset_viewer);
+        gui.add_ui_element(procedural_menu);
+        gui.add_ui_element(ShadowSettingsMenu {});
+        gui.add_ui_element(RenderSettingsMenu {});
         gui
     }
 
@@ -62,9 +100,9 @@ impl IGui for Gui {
         "Void"
     }
 
-    fn show(&mut self, config: &mut Config, ctx: &three_d::egui::Context) {
+    fn show(&mut self, config: &mut Config, camera: &Camera, ctx: &three_d::egui::Context) {
         for child in &mut self.children {
-            child.show(config, ctx);
+            child.show(config, camera, ctx);
         }
     }
 }
@@ -72,23 +110,153 @@ impl IGui for Gui {
 pub struct MainMenu {}
 
 impl IGui for MainMenu {
-    fn show(&mut self, config: &mut Config, ctx: &three_d::egui::Context) {
+    fn show(&mut self, config: &mut Config, _camera: &Camera, ctx: &three_d::egui::Context) {
         TopBottomPanel::top("TOp Menu").show(ctx, |ui| {
             ui.horizontal(|ui| {
                 if ui.button("Assets").clicked() {
                     config.asset_menu = !config.asset_menu;
                 }
+                if ui.button("Procedural").clicked() {
+                    config.procedural_menu = !config.procedural_menu;
+                }
+                if ui.button("Shadows").clicked() {
+                    config.shadow_settings_menu = !config.shadow_settings_menu;
+                }
+                if ui.button("Render").clicked() {
+                    config.render_settings_menu = !config.render_settings_menu;
+                }
+                ui.separator();
+                ui.label(format!(
+                    "Drawn: {}  Culled: {}",
+                    config.cull_stats.drawn, config.cull_stats.culled
+                ));
             });
         });
     }
 }
 
+/// Side length, in pixels, of a cached asset-list thumbnail.
+const THUMBNAIL_SIZE: u32 = 48;
+
+/// A thumbnail rendered for a model, kept alongside the material/transform it was
+/// rendered with so `AssetMenu` can tell a stale thumbnail from a current one without
+/// re-rendering every frame.
+struct CachedThumbnail {
+    transform: Mat4,
+    albedo: Srgba,
+    metallic: f32,
+    roughness: f32,
+    texture: TextureHandle,
+}
+
 pub struct AssetMenu {
     resources: Arc<RwLock<Resources>>,
+    thumbnails: RefCell<HashMap<NodePath, CachedThumbnail>>,
+}
+
+impl AssetMenu {
+    /// Returns this model's cached thumbnail, re-rendering it first if it's missing or
+    /// stale (its transform or material — anything `AssetViewer` lets the user edit —
+    /// has changed since the cached render).
+    fn thumbnail(
+        &self,
+        ctx: &three_d::egui::Context,
+        gl: &three_d::Context,
+        path: &NodePath,
+        model: &ModelEntry,
+    ) -> TextureHandle {
+        let material = &model.normal_mesh.material;
+        let mut cache = self.thumbnails.borrow_mut();
+        let stale = match cache.get(path) {
+            Some(cached) => {
+                cached.transform != model.transform
+                    || cached.albedo != material.albedo
+                    || cached.metallic != material.metallic
+                    || cached.roughness != material.roughness
+            }
+            None => true,
+        };
+        if stale {
+            let texture = model.render_thumbnail(gl, THUMBNAIL_SIZE);
+            let pixels: Vec<u8> = texture
+                .as_color_target(None)
+                .read::<[u8; 4]>()
+                .into_iter()
+                .flatten()
+                .collect();
+            let image = ColorImage::from_rgba_unmultiplied(
+                [THUMBNAIL_SIZE as usize, THUMBNAIL_SIZE as usize],
+                &pixels,
+            );
+            let handle = ctx.load_texture(format!("thumbnail-{path:?}"), image, TextureOptions::LINEAR);
+            cache.insert(
+                path.clone(),
+                CachedThumbnail {
+                    transform: model.transform,
+                    albedo: material.albedo,
+                    metallic: material.metallic,
+                    roughness: material.roughness,
+                    texture: handle,
+                },
+            );
+        }
+        cache[path].texture.clone()
+    }
+
+    /// Renders one scene node as a collapsible tree entry, recursing into its children.
+    /// Leaf nodes and nodes carrying a model are selectable, and show a thumbnail when
+    /// they carry a model; pure group nodes are not selectable.
+    fn show_node(
+        &self,
+        ctx: &three_d::egui::Context,
+        gl: &three_d::Context,
+        ui: &mut Ui,
+        node: &SceneNode,
+        path: NodePath,
+        config: &mut Config,
+    ) {
+        if node.children.is_empty() {
+            let selected = config.selected_asset.as_ref() == Some(&path);
+            ui.horizontal(|ui| {
+                if let Some(model) = &node.model {
+                    ui.image(SizedTexture::new(
+                        self.thumbnail(ctx, gl, &path, model).id(),
+                        Vec2::splat(THUMBNAIL_SIZE as f32),
+                    ));
+                }
+                if ui.selectable_label(selected, &node.name).clicked() {
+                    config.selected_asset = Some(path);
+                }
+            });
+            return;
+        }
+
+        CollapsingHeader::new(&node.name)
+            .default_open(false)
+            .show(ui, |ui| {
+                if let Some(model) = &node.model {
+                    let selected = config.selected_asset.as_ref() == Some(&path);
+                    ui.horizontal(|ui| {
+                        ui.image(SizedTexture::new(
+                            self.thumbnail(ctx, gl, &path, model).id(),
+                            Vec2::splat(THUMBNAIL_SIZE as f32),
+                        ));
+                        if ui.selectable_label(selected, "(this node)").clicked() {
+                            config.selected_asset = Some(path.clone());
+                        }
+                    });
+                }
+                for (idx, child) in node.children.iter().enumerate() {
+                    let mut child_path = path.clone();
+                    child_path.push(idx);
+                    self.show_node(ctx, gl, ui, child, child_path, config);
+                }
+            });
+    }
 }
 
 impl IGui for AssetMenu {
-    fn show(&mut self, config: &mut Config, ctx: &three_d::egui::Context) {
+    fn show(&mut self, config: &mut Config, _camera: &Camera, ctx: &three_d::egui::Context) {
         use three_d::egui::*;
         if config.asset_menu {
             SidePanel::right("Asset Panel").show(ctx, |ui| {
@@ -96,8 +264,8 @@ impl IGui for AssetMenu {
                     if ui.button("Asset Folder").clicked() {
                         if let  Some(path) = rfd::FileDialog::new().pick_folder() {
                             let mut res = self.resources.write().unwrap();
-                            if let Ok(models) = load_models(&res.ctx, path) {
-                                res.models.extend(models);
+                            if let Ok(roots) = load_models(&res.ctx, path) {
+                                res.roots.extend(roots);
                             }
                         }
                     }
@@ -105,20 +273,17 @@ impl IGui for AssetMenu {
                     if ui.button("Import Asset").clicked() {
                         if let  Some(path) = rfd::FileDialog::new().pick_file() {
                             let mut res = self.resources.write().unwrap();
-                            if let Ok(models) = load_models(&res.ctx, path) {
-                                res.models.extend(models);
+                            if let Ok(roots) = load_models(&res.ctx, path) {
+                                res.roots.extend(roots);
                             }
                         }
                     }
                     ui.separator();
                     ScrollArea::vertical().show(ui, |ui| {
                         let res = self.resources.read().unwrap();
-                        for (idx, _model) in res.models.iter().enumerate() {
-                            let name = format!("Segment {idx}");
-                            if ui.button(name).clicked() {
-                                println!("Selecting asset  {idx}");
-                                config.selected_asset = Some(idx);
-                            }
+                        let gl = res.ctx.clone();
+                        for (idx, root) in res.roots.iter().enumerate() {
+                            self.show_node(ctx, &gl, ui, root, vec![idx], config);
                         }
                     });
                 });
@@ -128,16 +293,40 @@ impl IGui for AssetMenu {
 }
 
 pub struct AssetViewer {
-    resources: Arc<RwLock<Resources>>
+    resources: Arc<RwLock<Resources>>,
+    gizmo: Gizmo,
+}
+
+/// Multiplies the local transforms of every node strictly above `path` to find the
+/// world transform of `path`'s parent, so an edit can be converted back to a local one.
+fn parent_world_transform(roots: &[SceneNode], path: &[usize]) -> Mat4 {
+    let mut world = Mat4::from_scale(1.0);
+    let mut siblings = roots;
+    for &idx in &path[..path.len().saturating_sub(1)] {
+        let Some(node) = siblings.get(idx) else {
+            break;
+        };
+        world = world * node.local_transform;
+        siblings = &node.children;
+    }
+    world
 }
 
 #[rustfmt::skip]
 impl IGui for AssetViewer {
-    fn show(&mut self, config: &mut Config, ctx: &three_d::egui::Context) {
-        if let Some(idx) = config.selected_asset {
+    fn show(&mut self, config: &mut Config, camera: &Camera, ctx: &three_d::egui::Context) {
+        if let Some(path) = config.selected_asset.clone() {
             let mut res = self.resources.write().unwrap();
-            let models = &mut res.models;
-            let selected_model = &mut models[idx];
+            let parent_world = parent_world_transform(&res.roots, &path);
+            let Some(node) = res.node_mut(&path) else {
+                config.selected_asset = None;
+                return;
+            };
+            let Some(selected_model) = node.model.as_mut() else {
+                return;
+            };
+            let world_transform = parent_world * node.local_transform;
+            let mut new_world_transform = None;
             SidePanel::left("Asset Menu").show(ctx, |ui| {
                 if ui.button("Close").clicked() {
                     config.selected_asset = None;
@@ -152,7 +341,192 @@ impl IGui for AssetViewer {
                 ui.add(Slider::new::<u8>(&mut selected_model.normal_mesh.material.albedo.g, 0..=255).text("G"));
                 ui.add(Slider::new::<u8>(&mut selected_model.normal_mesh.material.albedo.b, 0..=255).text("B"));
                 ui.add(Slider::new::<u8>(&mut selected_model.normal_mesh.material.albedo.a, 0..=255).text("A"));
+
+                ui.separator();
+                ui.label("Transform Gizmo");
+                new_world_transform = self.gizmo.show(ui, camera, world_transform);
             });
+            if let Some(new_world_transform) = new_world_transform {
+                let parent_inverse = parent_world.invert().unwrap_or(Mat4::from_scale(1.0));
+                node.local_transform = parent_inverse * new_world_transform;
+                res.update_world_transforms();
+            }
+        }
+    }
+}
+
+#[derive(PartialEq)]
+enum FieldKind {
+    SphereSdf,
+    Gyroid,
+    Noise,
+}
+
+pub struct ProceduralMenu {
+    resources: Arc<RwLock<Resources>>,
+    field_kind: FieldKind,
+    radius: f32,
+    scale: f32,
+    seed: u32,
+    resolution: usize,
+    isovalue: f32,
+}
+
+impl ProceduralMenu {
+    pub fn new(resources: Arc<RwLock<Resources>>) -> Self {
+        Self {
+            resources,
+            field_kind: FieldKind::SphereSdf,
+            radius: 1.0,
+            scale: 1.0,
+            seed: 0,
+            resolution: 32,
+            isovalue: 0.0,
+        }
+    }
+
+    fn field(&self) -> ScalarField {
+        match self.field_kind {
+            FieldKind::SphereSdf => ScalarField::SphereSdf {
+                radius: self.radius,
+            },
+            FieldKind::Gyroid => ScalarField::Gyroid { scale: self.scale },
+            FieldKind::Noise => ScalarField::Noise {
+                scale: self.scale,
+                seed: self.seed,
+            },
+        }
+    }
+}
+
+impl IGui for ProceduralMenu {
+    fn show(&mut self, config: &mut Config, _camera: &Camera, ctx: &three_d::egui::Context) {
+        if !config.procedural_menu {
+            return;
+        }
+        Window::new("Procedural Mesh").show(ctx, |ui| {
+            ui.radio_value(&mut self.field_kind, FieldKind::SphereSdf, "Sphere SDF");
+            ui.radio_value(&mut self.field_kind, FieldKind::Gyroid, "Gyroid");
+            ui.radio_value(&mut self.field_kind, FieldKind::Noise, "Noise");
+
+            match self.field_kind {
+                FieldKind::SphereSdf => {
+                    ui.add(Slider::new(&mut self.radius, 0.1..=5.0).text("Radius"));
+                }
+                FieldKind::Gyroid => {
+                    ui.add(Slider::new(&mut self.scale, 0.1..=10.0).text("Scale"));
+                }
+                FieldKind::Noise => {
+                    ui.add(Slider::new(&mut self.scale, 0.1..=10.0).text("Scale"));
+                    ui.add(Slider::new(&mut self.seed, 0..=1000).text("Seed"));
+                }
+            }
+
+            ui.add(Slider::new(&mut self.resolution, 4..=128).text("Grid resolution"));
+            ui.add(Slider::new(&mut self.isovalue, -2.0..=2.0).text("Isovalue"));
+
+            if ui.button("Generate").clicked() {
+                let field = self.field();
+                let half_extent = field.suggested_half_extent();
+                let grid = Grid {
+                    resolution: (self.resolution, self.resolution, self.resolution),
+                    min: three_d::vec3(-half_extent, -half_extent, -half_extent),
+                    max: three_d::vec3(half_extent, half_extent, half_extent),
+                };
+                let cpu_mesh = procedural::marching_cubes(&field, &grid, self.isovalue);
+                let mut res = self.resources.write().unwrap();
+                let model = ModelEntry::new(&res.ctx, cpu_mesh);
+                let idx = res.roots.len();
+                res.roots.push(SceneNode::with_model("Procedural Mesh", model));
+                config.selected_asset = Some(vec![idx]);
+            }
+        });
+    }
+}
+
+/// Live editor for each shadow-casting light's `ShadowConfig`, replacing the main loop's
+/// single hardcoded `generate_shadow_map(1024, ...)` call shared by every light.
+pub struct ShadowSettingsMenu {}
+
+impl IGui for ShadowSettingsMenu {
+    fn show(&mut self, config: &mut Config, _camera: &Camera, ctx: &three_d::egui::Context) {
+        if !config.shadow_settings_menu {
+            return;
+        }
+        Window::new("Shadow Settings").show(ctx, |ui| {
+            for (name, shadow_config) in SHADOW_CASTER_NAMES
+                .iter()
+                .zip(config.shadow_configs.iter_mut())
+            {
+                CollapsingHeader::new(*name).show(ui, |ui| {
+                    ui.checkbox(&mut shadow_config.enabled, "Enabled");
+                    ui.add(
+                        Slider::new(&mut shadow_config.map_resolution, 128..=4096)
+                            .text("Map resolution"),
+                    );
+                    ui.add(Slider::new(&mut shadow_config.bias, 0.0..=0.01).text("Depth bias"));
+
+                    ui.horizontal(|ui| {
+                        ui.label("Filter");
+                        ComboBox::new(format!("{name}_filter"), "")
+                            .selected_text(format!("{:?}", shadow_config.filter))
+                            .show_ui(ui, |ui| {
+                                for filter in [
+                                    ShadowFilterMode::None,
+                                    ShadowFilterMode::Hardware2x2,
+                                    ShadowFilterMode::Pcf,
+                                    ShadowFilterMode::Pcss,
+                                ] {
+                                    ui.selectable_value(
+                                        &mut shadow_config.filter,
+                                        filter,
+                                        format!("{filter:?}"),
+                                    );
+                                }
+                            });
+                    });
+
+                    if shadow_config.filter == ShadowFilterMode::Pcss {
+                        ui.add(
+                            Slider::new(&mut shadow_config.light_size, 0.01..=1.0)
+                                .text("Light size"),
+                        );
+                    }
+                });
+            }
+        });
+    }
+}
+
+/// Picks between the normal lit path and the debug-channel pipeline, and, while the
+/// latter is active, which G-buffer channel to inspect full-screen instead of the lit
+/// result. This is not a Forward/Deferred toggle — see `render_mode`'s module doc.
+pub struct RenderSettingsMenu {}
+
+impl IGui for RenderSettingsMenu {
+    fn show(&mut self, config: &mut Config, _camera: &Camera, ctx: &three_d::egui::Context) {
+        if !config.render_settings_menu {
+            return;
         }
+        Window::new("Render Settings").show(ctx, |ui| {
+            ui.label("Pipeline");
+            ui.radio_value(&mut config.material_type, MaterialType::Forward, "Forward");
+            ui.radio_value(&mut config.material_type, MaterialType::DebugChannels, "Debug Channels");
+
+            ui.separator();
+            ui.label("Debug channel");
+            ui.add_enabled_ui(config.material_type != MaterialType::Forward, |ui| {
+                for channel in [
+                    MaterialType::Color,
+                    MaterialType::Position,
+                    MaterialType::Normal,
+                    MaterialType::Depth,
+                    MaterialType::Orm,
+                    MaterialType::Uv,
+                ] {
+                    ui.radio_value(&mut config.material_type, channel, format!("{channel:?}"));
+                }
+            });
+        });
     }
 }
@@ -8,18 +8,28 @@ use std::{
     sync::{Arc, RwLock},
 };
 use three_d::{
-    core::Context, vec3, Camera, ColorMaterial, CpuMaterial, CpuMesh, Cull, FromCpuMaterial,
-    Geometry, Gm, InnerSpace, InstancedMesh, Instances, Light, Mat4, Material, Mesh, Object,
-    PhysicalMaterial, Quat, RenderTarget, Srgba, Vec3,
+    core::Context, degrees, vec3, AmbientLight, AxisAlignedBoundingBox, Camera, ClearState,
+    ColorMaterial, CpuMaterial, CpuMesh, Cull, DepthTexture2D, DirectionalLight, FromCpuMaterial,
+    Geometry, Gm, InnerSpace, InstancedMesh, Instances, Interpolation, Light, Mat4, Material, Mesh,
+    Object, PhysicalMaterial, Quat, RenderTarget, Srgba, Texture2D, Vec3, Viewport, Wrapping,
 };
 
+pub mod culling;
 pub mod gui;
+pub mod procedural;
+pub mod render_mode;
+pub mod scene;
+pub mod shadow;
+
+pub use culling::CullStats;
+pub use render_mode::MaterialType;
+pub use scene::SceneNode;
 
 static DARK: RangeInclusive<u8> = 0..=125;
 static BRIGHT: RangeInclusive<u8> = 126..=255;
 
 pub struct Resources {
-    pub models: Vec<ModelEntry>,
+    pub roots: Vec<SceneNode>,
     pub ctx: Context,
 }
 
@@ -32,10 +42,36 @@ pub enum RenderMode {
 impl Resources {
     pub fn new(ctx: Context) -> Self {
         Self {
-            models: Vec::new(),
+            roots: Vec::new(),
             ctx,
         }
     }
+
+    /// Recomputes every node's world transform for this frame, from the roots down.
+    pub fn update_world_transforms(&mut self) {
+        for root in &mut self.roots {
+            root.update_world_transform(Mat4::from_scale(1.0));
+        }
+    }
+
+    pub fn node_mut(&mut self, path: &[usize]) -> Option<&mut SceneNode> {
+        let (first, rest) = path.split_first()?;
+        self.roots.get_mut(*first)?.node_mut(rest)
+    }
+
+    /// Depth-first iteration over every model in the scene, for rendering/culling passes.
+    pub fn visit_models_mut(&mut self, mut f: impl FnMut(&mut ModelEntry)) {
+        for root in &mut self.roots {
+            root.visit_models_mut(&mut f);
+        }
+    }
+
+    /// Mutable references to every model in the scene, for passes that need to hold
+    /// them all at once (culling, the debug-channel geometry pass) rather than visiting one
+    /// at a time.
+    pub fn models_mut(&mut self) -> Vec<&mut ModelEntry> {
+        self.roots.iter_mut().flat_map(SceneNode::models_mut).collect()
+    }
 }
 
 fn get_rand_rgba() -> [u8; 4] {
@@ -67,6 +103,12 @@ pub struct ModelEntry {
     pub wireframe_vertices: Gm<InstancedMesh, PhysicalMaterial>,
     pub wireframe_edges: Gm<InstancedMesh, PhysicalMaterial>,
     pub render_mode: RenderMode,
+    /// World transform applied to `normal_mesh`, `wireframe_edges` and `wireframe_vertices`.
+    /// Set from the owning `SceneNode`'s world transform by `Resources::update_world_transforms`.
+    pub transform: Mat4,
+    /// Local-space bounding box, cached at load time so the culling pass doesn't have to
+    /// walk the mesh's vertices every frame.
+    local_aabb: AxisAlignedBoundingBox,
 }
 
 impl ModelEntry {
@@ -103,6 +145,7 @@ impl ModelEntry {
 
         let model_material = new_phys_mat(ctx);
         let normal_mesh = Gm::new(Mesh::new(&ctx, &cpu_mesh), model_material);
+        let local_aabb = cpu_mesh.compute_aabb();
 
         Self {
             cpu_mesh,
@@ -110,9 +153,86 @@ impl ModelEntry {
             wireframe_vertices,
             wireframe_edges,
             render_mode: RenderMode::Normal,
+            transform: Mat4::from_scale(1.0),
+            local_aabb,
         }
     }
 
+    /// This model's axis-aligned bounding box in world space, used by the culling pass.
+    pub fn world_aabb(&self) -> AxisAlignedBoundingBox {
+        let mut aabb = self.local_aabb;
+        aabb.transform(&self.transform);
+        aabb
+    }
+
+    /// The CPU-side mesh this model was built from, for passes that need to re-derive
+    /// per-vertex data (e.g. the debug-channel pass's G-buffer channel encoding).
+    pub(crate) fn cpu_mesh(&self) -> &CpuMesh {
+        &self.cpu_mesh
+    }
+
+    /// This model's already-uploaded GPU geometry, for passes (e.g. the shadow overlay)
+    /// that want to draw the same mesh again with a different material without paying
+    /// for a fresh `Mesh::new` upload every frame.
+    pub(crate) fn gpu_mesh(&self) -> &Mesh {
+        &self.normal_mesh.geometry
+    }
+
+    /// Applies a new world transform to this model, keeping the mesh, wireframe edges
+    /// and wireframe vertices in sync so the gizmo, the solid mesh and its overlay move together.
+    pub fn set_transformation(&mut self, transform: Mat4) {
+        self.transform = transform;
+        self.normal_mesh.set_transformation(transform);
+        self.wireframe_edges.set_transformation(transform);
+        self.wireframe_vertices.set_transformation(transform);
+    }
+
+    /// Renders this model alone into an offscreen `size`×`size` texture, framed by a
+    /// camera fit to its bounding box and lit by a small default rig, for use as an
+    /// asset-list thumbnail. Always re-renders; callers that want to avoid re-rendering
+    /// every frame should cache the result themselves, keyed on whatever they consider
+    /// to make a model's appearance stale (e.g. its transform or material).
+    pub fn render_thumbnail(&self, ctx: &Context, size: u32) -> Texture2D {
+        // `normal_mesh` already carries the world transform (`set_transformation`), so the
+        // framing camera must use the world-space box, not `local_aabb`, or a moved/scaled
+        // model renders off-center or empty.
+        let aabb = self.world_aabb();
+        let center = aabb.center();
+        let radius = aabb.size().magnitude().max(0.001) * 0.5;
+        let eye = center + vec3(1.0, 1.0, 1.0).normalize_to(radius * 2.5);
+        let camera = Camera::new_perspective(
+            Viewport::new_at_origo(size, size),
+            eye,
+            center,
+            vec3(0.0, 1.0, 0.0),
+            degrees(45.0),
+            radius * 0.01,
+            radius * 10.0,
+        );
+
+        let ambient = AmbientLight::new(ctx, 0.4, Srgba::WHITE);
+        let key_light = DirectionalLight::new(ctx, 1.0, Srgba::WHITE, &vec3(-1.0, -1.0, -1.0));
+        let lights: [&dyn Light; 2] = [&ambient, &key_light];
+
+        let texture = Texture2D::new_empty::<[u8; 4]>(
+            ctx,
+            size,
+            size,
+            Interpolation::Linear,
+            Interpolation::Linear,
+            None,
+            Wrapping::ClampToEdge,
+            Wrapping::ClampToEdge,
+        );
+        let depth_texture =
+            DepthTexture2D::new::<f32>(ctx, size, size, Wrapping::ClampToEdge, Wrapping::ClampToEdge);
+        let target = RenderTarget::new(texture.as_color_target(None), depth_texture.as_depth_target());
+        target.clear(ClearState::color_and_depth(0.1, 0.1, 0.1, 1.0, 1.0));
+        target.render(&camera, &self.normal_mesh, &lights);
+
+        texture
+    }
+
     pub fn render(&mut self, target: &RenderTarget, camera: &Camera, lights: &[&dyn Light]) {
         match self.render_mode {
             RenderMode::Normal => {
@@ -131,29 +251,24 @@ impl ModelEntry {
     }
 }
 
-fn get_model(ctx: &three_d::Context, path: PathBuf) -> Result<ModelEntry> {
-    let model: CpuMesh = three_d_asset::io::load(&[path]).unwrap().deserialize("")?;
-    let model = ModelEntry::new(ctx, model);
-    return Ok(model);
-}
-
-pub fn load_models(ctx: &three_d::Context, path: PathBuf) -> Result<Vec<ModelEntry>> {
+/// Loads `path` into one or more scene graph roots. A single glTF/glb file keeps its
+/// node hierarchy and local transforms; a directory is imported file-by-file, each
+/// contributing its own root(s) to the returned list.
+pub fn load_models(ctx: &three_d::Context, path: PathBuf) -> Result<Vec<SceneNode>> {
     if !path.is_dir() {
-        let model: CpuMesh = three_d_asset::io::load(&[path]).unwrap().deserialize("")?;
-        let model = ModelEntry::new(ctx, model);
-        return Ok(vec![model]);
+        return scene::load_file(ctx, path);
     }
 
-    let models = Arc::new(RwLock::new(Vec::new()));
+    let roots = Arc::new(RwLock::new(Vec::new()));
 
     for entry in fs::read_dir(path).unwrap() {
         let entry = entry?;
         std::thread::scope(|_| {
-            let models = Arc::clone(&models);
-            match get_model(&ctx.clone(), entry.path()) {
-                Ok(model) => {
-                    let mut models_write = models.write().unwrap();
-                    models_write.push(model);
+            let roots = Arc::clone(&roots);
+            match scene::load_file(&ctx.clone(), entry.path()) {
+                Ok(mut new_roots) => {
+                    let mut roots_write = roots.write().unwrap();
+                    roots_write.append(&mut new_roots);
                 }
                 Err(msg) => {
                     println!("Couldnt load {msg}");
@@ -162,9 +277,9 @@ pub fn load_models(ctx: &three_d::Context, path: PathBuf) -> Result<Vec<ModelEnt
         });
     }
 
-    let mut models_write = models.write().unwrap();
-    let models = std::mem::take(&mut *models_write);
-    Ok(models)
+    let mut roots_write = roots.write().unwrap();
+    let roots = std::mem::take(&mut *roots_write);
+    Ok(roots)
 }
 
 fn edge_transformations(cpu_mesh: &CpuMesh) -> Instances {
@@ -4,22 +4,16 @@ async fn main() {
     run().await;
 }
 
-#[derive(Debug, Eq, PartialEq)]
-enum MaterialType {
-    Position,
-    Normal,
-    Color,
-    Depth,
-    Orm,
-    Uv,
-    Forward,
-    Deferred,
-}
-
 use std::sync::{Arc, RwLock};
 
 use three_d::*;
+use viewer::culling::{CullStats, Frustum, HiZPyramid};
 use viewer::gui::{Config, Gui, IGui, MainMenu};
+use viewer::render_mode::{ChannelVisualizer, GBuffer, MaterialType};
+use viewer::shadow::{
+    directional_shadow_camera, draw_shadow_overlay, spot_shadow_camera, ShadowCaster, ShadowConfig,
+    ShadowMap,
+};
 use viewer::{load_models, ModelEntry, RenderMode, Resources};
 
 pub async fn run() {
@@ -59,20 +53,27 @@ pub async fn run() {
     let mut loaded = three_d_asset::io::load_async(&["./assets/studio.hdr"])
         .await
         .unwrap();
-    let res = Arc::new(RwLock::new(Resources::new(context.clone())));
-    let mut main_menu = Gui::new(res);
+    let resources = Arc::new(RwLock::new(Resources::new(context.clone())));
+    let mut main_menu = Gui::new(Arc::clone(&resources));
+
+    let directional0_direction = vec3(0.0, -1.0, 0.0);
+    let directional1_direction = vec3(0.0, -1.0, 0.0);
+    let spot0_position = vec3(0.0, 0.0, 0.0);
+    let spot0_direction = vec3(0.0, -1.0, 0.0);
+    let spot0_cone_angle_degrees = 25.0_f32;
+    let spot0_cone_angle = degrees(spot0_cone_angle_degrees);
 
     let mut ambient = AmbientLight::new(&context, 0.2, Srgba::WHITE);
-    let mut directional0 = DirectionalLight::new(&context, 1.0, Srgba::RED, &vec3(0.0, -1.0, 0.0));
+    let mut directional0 = DirectionalLight::new(&context, 1.0, Srgba::RED, &directional0_direction);
     let mut directional1 =
-        DirectionalLight::new(&context, 1.0, Srgba::GREEN, &vec3(0.0, -1.0, 0.0));
+        DirectionalLight::new(&context, 1.0, Srgba::GREEN, &directional1_direction);
     let mut spot0 = SpotLight::new(
         &context,
         2.0,
         Srgba::BLUE,
-        &vec3(0.0, 0.0, 0.0),
-        &vec3(0.0, -1.0, 0.0),
-        degrees(25.0),
+        &spot0_position,
+        &spot0_direction,
+        spot0_cone_angle,
         Attenuation {
             constant: 0.1,
             linear: 0.001,
@@ -103,11 +104,15 @@ pub async fn run() {
     );
 
     // main loop
-    let mut shadows_enabled = true;
     let mut config = Config::default();
 
     let model_wireframe = false;
 
+    let viewport = window.viewport();
+    let mut gbuffer = GBuffer::new(&context, viewport.width, viewport.height);
+    let mut channel_visualizer = ChannelVisualizer::new(&context);
+    let mut hi_z = HiZPyramid::new(&context, viewport.width, viewport.height);
+
     window.render_loop(move |mut frame_input| {
         let mut panel_width = 0.0;
         gui.update(
@@ -116,7 +121,7 @@ pub async fn run() {
             frame_input.viewport,
             frame_input.device_pixel_ratio,
             |gui_context| {
-                main_menu.show(&mut config, &gui_context);
+                main_menu.show(&mut config, &camera, &gui_context);
                 panel_width = gui_context.used_rect().width();
             },
         );
@@ -131,33 +136,151 @@ pub async fn run() {
             },
         );
 
-        // Draw
-        if shadows_enabled {
-            for model in &models {
-                directional0.generate_shadow_map(1024, &*model.normal_mesh);
-                directional1.generate_shadow_map(1024, &*model.normal_mesh);
-                spot0.generate_shadow_map(1024, &*model.normal_mesh);
+        // Cull: frustum-test every model first, then depth-prepass and Hi-Z test the
+        // survivors, so occluded models skip both the color and the shadow passes.
+        let frustum = Frustum::from_camera(&camera);
+        let mut res = resources.write().unwrap();
+        // Push every node's local transform down into its model before anything reads
+        // `world_aabb()`/`transform`, so a freshly imported glTF hierarchy (or a procedural
+        // mesh added this frame) renders in place instead of at the identity default.
+        res.update_world_transforms();
+        let mut all_models = res.models_mut();
+        let mut stats = CullStats::default();
+
+        let mut frustum_visible: Vec<&mut ModelEntry> = Vec::new();
+        for model in all_models.drain(..) {
+            if frustum.intersects(&model.world_aabb()) {
+                frustum_visible.push(model);
+            } else {
+                stats.culled += 1;
             }
         }
 
-        let lights = [
-            &ambient as &dyn Light,
-            &spot0,
-            &directional0,
-            &directional1,
-            &point0,
-            &point1,
-        ];
+        hi_z.depth_prepass(&camera, &mut frustum_visible);
+        hi_z.build();
+
+        let mut visible: Vec<&mut ModelEntry> = Vec::new();
+        for model in frustum_visible.drain(..) {
+            if hi_z.visible(&camera, &model.world_aabb()) {
+                visible.push(model);
+            } else {
+                stats.culled += 1;
+            }
+        }
+        stats.drawn = visible.len();
+        config.cull_stats = stats;
+
+        // Draw
+        let [directional0_shadows, directional1_shadows, spot0_shadows] = config.shadow_configs;
+        // One call per light with every visible mesh: each call rebuilds the light's
+        // shadow map from scratch, so calling it per model would keep only the last one.
+        if directional0_shadows.enabled {
+            directional0.generate_shadow_map(directional0_shadows.map_resolution, visible.iter().map(|m| &*m.normal_mesh));
+        }
+        if directional1_shadows.enabled {
+            directional1.generate_shadow_map(directional1_shadows.map_resolution, visible.iter().map(|m| &*m.normal_mesh));
+        }
+        if spot0_shadows.enabled {
+            spot0.generate_shadow_map(spot0_shadows.map_resolution, visible.iter().map(|m| &*m.normal_mesh));
+        }
+
+        // Each enabled caster also gets its own light-space depth buffer, sampled per
+        // fragment by `draw_shadow_overlay`'s `ShadowMaterial` below, so `ShadowConfig`'s
+        // `filter`/`bias`/`light_size` actually soften the shadow edge the screen shows,
+        // instead of only affecting three_d's fixed built-in shadow term above. A light
+        // whose shadows are disabled gets `ShadowMap::disabled` instead, skipping both the
+        // light-camera framing and the depth render this frame.
+        let directional0_map = build_shadow_map(
+            &context,
+            directional0_shadows,
+            || directional_shadow_camera(viewport, directional0_direction, &visible),
+            &mut visible,
+        );
+        let directional1_map = build_shadow_map(
+            &context,
+            directional1_shadows,
+            || directional_shadow_camera(viewport, directional1_direction, &visible),
+            &mut visible,
+        );
+        let spot0_map = build_shadow_map(
+            &context,
+            spot0_shadows,
+            || {
+                spot_shadow_camera(
+                    viewport,
+                    spot0_position,
+                    spot0_direction,
+                    spot0_cone_angle_degrees.to_radians(),
+                    &visible,
+                )
+            },
+            &mut visible,
+        );
 
         let screen = frame_input.screen();
         screen.clear(ClearState::default());
 
-        for model in &mut models {
-            model.render(&screen, &camera, &lights);
+        if config.material_type == MaterialType::Forward {
+            let lights = [
+                &ambient as &dyn Light,
+                &directional0,
+                &directional1,
+                &spot0,
+                &point0,
+                &point1,
+            ];
+            let all_casters = [
+                (&directional0_map, directional0_shadows),
+                (&directional1_map, directional1_shadows),
+                (&spot0_map, spot0_shadows),
+            ];
+            let shadow_casters: Vec<ShadowCaster> = all_casters
+                .iter()
+                .filter(|(_, config)| config.enabled)
+                .map(|(map, config)| ShadowCaster { map, config: *config })
+                .collect();
+            for model in &mut visible {
+                model.render(&screen, &camera, &lights);
+                draw_shadow_overlay(&screen, &camera, &**model, shadow_casters.clone());
+            }
+        } else {
+            let lights = [
+                &ambient as &dyn Light,
+                &spot0,
+                &directional0,
+                &directional1,
+                &point0,
+                &point1,
+            ];
+            gbuffer.geometry_pass(&context, &camera, &mut visible, &lights, config.material_type);
+            match gbuffer.color_channel(config.material_type) {
+                Some(texture) => channel_visualizer.show(&screen, &camera, texture),
+                None => channel_visualizer.show(&screen, &camera, &gbuffer.color),
+            }
         }
+        drop(res);
 
         screen.write(|| gui.render()).unwrap();
 
         FrameOutput::default()
     });
 }
+
+/// Builds `config`'s shadow map for this frame, or hands back a cheap 1x1 placeholder
+/// when the light's shadows are disabled. `light_camera` is a closure rather than an
+/// already-built `Camera` so a disabled light also skips the light-camera framing itself
+/// (`directional_shadow_camera`/`spot_shadow_camera`'s bounding-sphere walk over every
+/// visible model), not just the depth render.
+fn build_shadow_map(
+    ctx: &Context,
+    config: ShadowConfig,
+    light_camera: impl FnOnce() -> Camera,
+    casters: &mut [&mut ModelEntry],
+) -> ShadowMap {
+    if !config.enabled {
+        return ShadowMap::disabled(ctx);
+    }
+    let mut map = ShadowMap::new(ctx, config.map_resolution);
+    map.build(&light_camera(), casters);
+    map
+}
@@ -0,0 +1,211 @@
+//! Generates `CpuMesh` isosurfaces from a sampled scalar field (SDFs, noise, ...) via
+//! classic marching cubes, so users can create geometry without importing a file.
+
+mod tables;
+
+use three_d::{vec3, CpuMesh, Indices, Positions, Vec3};
+use tables::{EDGE_TABLE, TRI_TABLE};
+
+/// A built-in scalar field to sample when generating a procedural mesh.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScalarField {
+    /// Signed distance to a sphere of the given radius, centered at the origin.
+    SphereSdf { radius: f32 },
+    /// A gyroid minimal surface: `sin(x)cos(y) + sin(y)cos(z) + sin(z)cos(x)`.
+    Gyroid { scale: f32 },
+    /// 3D value noise, useful for blobby/organic shapes.
+    Noise { scale: f32, seed: u32 },
+}
+
+impl ScalarField {
+    pub fn sample(&self, p: Vec3) -> f32 {
+        match *self {
+            ScalarField::SphereSdf { radius } => p.x.hypot(p.y.hypot(p.z)) - radius,
+            ScalarField::Gyroid { scale } => {
+                let (x, y, z) = (p.x * scale, p.y * scale, p.z * scale);
+                x.sin() * y.cos() + y.sin() * z.cos() + z.sin() * x.cos()
+            }
+            ScalarField::Noise { scale, seed } => value_noise(p * scale, seed),
+        }
+    }
+
+    /// A cube `[-half, half]^3` sized to actually contain this field's isosurface, so
+    /// `marching_cubes` doesn't silently sample an empty mesh. `SphereSdf`'s surface sits
+    /// at `radius` from the origin; `Gyroid`'s period shrinks as `scale` grows, so a fixed
+    /// box would either miss a large, slowly-varying surface or undersample a tight one.
+    pub fn suggested_half_extent(&self) -> f32 {
+        match *self {
+            ScalarField::SphereSdf { radius } => (radius * 1.25).max(0.1),
+            ScalarField::Gyroid { scale } => (std::f32::consts::PI / scale.max(0.01)).max(0.1),
+            ScalarField::Noise { .. } => 2.0,
+        }
+    }
+}
+
+/// Cheap deterministic value noise (trilinear interpolation of per-lattice-point hashes),
+/// good enough for a "blobby" procedural field without pulling in a noise crate.
+fn value_noise(p: Vec3, seed: u32) -> f32 {
+    fn hash(x: i32, y: i32, z: i32, seed: u32) -> f32 {
+        let mut h = (x as u32)
+            .wrapping_mul(374761393)
+            .wrapping_add((y as u32).wrapping_mul(668265263))
+            .wrapping_add((z as u32).wrapping_mul(2147483647))
+            .wrapping_add(seed.wrapping_mul(2246822519));
+        h = (h ^ (h >> 13)).wrapping_mul(1274126177);
+        h ^= h >> 16;
+        (h as f32 / u32::MAX as f32) * 2.0 - 1.0
+    }
+
+    let (x0, y0, z0) = (p.x.floor() as i32, p.y.floor() as i32, p.z.floor() as i32);
+    let (tx, ty, tz) = (p.x - p.x.floor(), p.y - p.y.floor(), p.z - p.z.floor());
+
+    let c000 = hash(x0, y0, z0, seed);
+    let c100 = hash(x0 + 1, y0, z0, seed);
+    let c010 = hash(x0, y0 + 1, z0, seed);
+    let c110 = hash(x0 + 1, y0 + 1, z0, seed);
+    let c001 = hash(x0, y0, z0 + 1, seed);
+    let c101 = hash(x0 + 1, y0, z0 + 1, seed);
+    let c011 = hash(x0, y0 + 1, z0 + 1, seed);
+    let c111 = hash(x0 + 1, y0 + 1, z0 + 1, seed);
+
+    let x00 = c000 + (c100 - c000) * tx;
+    let x10 = c010 + (c110 - c010) * tx;
+    let x01 = c001 + (c101 - c001) * tx;
+    let x11 = c011 + (c111 - c011) * tx;
+    let y0 = x00 + (x10 - x00) * ty;
+    let y1 = x01 + (x11 - x01) * ty;
+    y0 + (y1 - y0) * tz
+}
+
+/// A regular grid of `(nx, ny, nz)` cells spanning `[min, max]`, sampled once per cell corner.
+pub struct Grid {
+    pub resolution: (usize, usize, usize),
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Grid {
+    fn cell_size(&self) -> Vec3 {
+        vec3(
+            (self.max.x - self.min.x) / self.resolution.0 as f32,
+            (self.max.y - self.min.y) / self.resolution.1 as f32,
+            (self.max.z - self.min.z) / self.resolution.2 as f32,
+        )
+    }
+
+    fn corner(&self, x: usize, y: usize, z: usize) -> Vec3 {
+        let size = self.cell_size();
+        vec3(
+            self.min.x + x as f32 * size.x,
+            self.min.y + y as f32 * size.y,
+            self.min.z + z as f32 * size.z,
+        )
+    }
+}
+
+/// The 8 cube corners in the standard marching-cubes winding, as (x, y, z) offsets.
+const CORNER_OFFSETS: [(usize, usize, usize); 8] = [
+    (0, 0, 0),
+    (1, 0, 0),
+    (1, 1, 0),
+    (0, 1, 0),
+    (0, 0, 1),
+    (1, 0, 1),
+    (1, 1, 1),
+    (0, 1, 1),
+];
+
+/// The two corner indices each of the 12 cube edges connects.
+const EDGE_CORNERS: [(usize, usize); 12] = [
+    (0, 1),
+    (1, 2),
+    (2, 3),
+    (3, 0),
+    (4, 5),
+    (5, 6),
+    (6, 7),
+    (7, 4),
+    (0, 4),
+    (1, 5),
+    (2, 6),
+    (3, 7),
+];
+
+/// Generates an isosurface mesh for `field` over `grid` at the given `isovalue` using
+/// classic marching cubes.
+pub fn marching_cubes(field: &ScalarField, grid: &Grid, isovalue: f32) -> CpuMesh {
+    let (nx, ny, nz) = grid.resolution;
+    let mut positions = Vec::new();
+    let mut indices = Vec::new();
+
+    for cz in 0..nz {
+        for cy in 0..ny {
+            for cx in 0..nx {
+                let corner_pos: [Vec3; 8] =
+                    std::array::from_fn(|i| {
+                        let (ox, oy, oz) = CORNER_OFFSETS[i];
+                        grid.corner(cx + ox, cy + oy, cz + oz)
+                    });
+                let corner_val: [f32; 8] = std::array::from_fn(|i| field.sample(corner_pos[i]));
+
+                let mut case_index = 0u8;
+                for (i, &v) in corner_val.iter().enumerate() {
+                    if v < isovalue {
+                        case_index |= 1 << i;
+                    }
+                }
+
+                let edges_crossed = EDGE_TABLE[case_index as usize];
+                if edges_crossed == 0 {
+                    continue;
+                }
+
+                let mut edge_vertex = [vec3(0.0, 0.0, 0.0); 12];
+                for edge in 0..12 {
+                    if edges_crossed & (1 << edge) == 0 {
+                        continue;
+                    }
+                    let (a, b) = EDGE_CORNERS[edge];
+                    edge_vertex[edge] =
+                        interpolate_edge(corner_pos[a], corner_val[a], corner_pos[b], corner_val[b], isovalue);
+                }
+
+                for tri in TRI_TABLE[case_index as usize].chunks(3) {
+                    if tri[0] < 0 {
+                        break;
+                    }
+                    let base = positions.len() as u32;
+                    positions.push(edge_vertex[tri[0] as usize]);
+                    positions.push(edge_vertex[tri[1] as usize]);
+                    positions.push(edge_vertex[tri[2] as usize]);
+                    indices.push(base);
+                    indices.push(base + 1);
+                    indices.push(base + 2);
+                }
+            }
+        }
+    }
+
+    let mut mesh = CpuMesh {
+        positions: Positions::F32(positions),
+        indices: Indices::U32(indices),
+        ..Default::default()
+    };
+    mesh.compute_normals();
+    mesh
+}
+
+/// Linearly interpolates the point on edge `a`-`b` where the field crosses `isovalue`,
+/// falling back to the midpoint when both endpoints sample equally (degenerate edge).
+fn interpolate_edge(a: Vec3, val_a: f32, b: Vec3, val_b: f32, isovalue: f32) -> Vec3 {
+    let denom = val_b - val_a;
+    if denom.abs() < 1e-6 {
+        return vec3((a.x + b.x) * 0.5, (a.y + b.y) * 0.5, (a.z + b.z) * 0.5);
+    }
+    let t = (isovalue - val_a) / denom;
+    vec3(
+        a.x + t * (b.x - a.x),
+        a.y + t * (b.y - a.y),
+        a.z + t * (b.z - a.z),
+    )
+}
@@ -0,0 +1,286 @@
+//! The render-mode enum threaded through the main loop. `Forward` is the normal lit
+//! path; every other variant runs the debug-channel pipeline and picks which G-buffer
+//! channel to blit full-screen. There is no real deferred (geometry-then-lighting-pass)
+//! renderer here — see [`GBuffer`]'s doc comment.
+
+use three_d::{
+    vec2, vec3, Camera, ClearState, ColorMaterial, Context, CpuMesh, DepthTexture2D, Gm, Indices,
+    InnerSpace, Interpolation, Light, Mat3, Mat4, Mesh, Positions, RenderTarget, Srgba, Texture2D,
+    Texture2DRef, Viewport, Wrapping,
+};
+
+use crate::ModelEntry;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaterialType {
+    Position,
+    Normal,
+    Color,
+    Depth,
+    Orm,
+    Uv,
+    Forward,
+    /// Not a second rendering pipeline: picking any of the channel variants above routes
+    /// through [`GBuffer::geometry_pass`] instead of the plain forward draw, and this
+    /// variant is just "whichever channel is currently selected defaults to `Color`"
+    /// when none of them is. See [`GBuffer`]'s doc comment for why there's no real
+    /// lighting-resolve pass to compare `Forward` against.
+    DebugChannels,
+}
+
+impl MaterialType {
+    /// Whether this variant selects a single G-buffer channel to inspect, rather than
+    /// picking which pipeline renders the scene.
+    pub fn is_debug_channel(self) -> bool {
+        !matches!(self, MaterialType::Forward | MaterialType::DebugChannels)
+    }
+}
+
+/// World position, normal, albedo, ORM, UV and depth render targets, written by the
+/// geometry pass.
+///
+/// There is deliberately no lighting-resolve pass here that reads `position`/`normal`/
+/// `orm` back to relight the scene from the G-buffer — `color` is forward-shaded
+/// directly, so selecting any channel still shows exactly what `Forward` would have
+/// drawn underneath it. This struct exists purely so [`ChannelVisualizer`] has somewhere
+/// to inspect a model's world position, normal, depth, ORM or UVs; it is a debug
+/// visualizer, not an alternate rendering pipeline, which is why `MaterialType` no
+/// longer claims a `Deferred` variant next to `Forward`.
+pub struct GBuffer {
+    pub position: Texture2D,
+    pub normal: Texture2D,
+    pub color: Texture2D,
+    pub orm: Texture2D,
+    pub uv: Texture2D,
+    pub depth_visual: Texture2D,
+    pub depth: DepthTexture2D,
+    width: u32,
+    height: u32,
+}
+
+impl GBuffer {
+    pub fn new(context: &Context, width: u32, height: u32) -> Self {
+        let channel = || {
+            Texture2D::new_empty::<[f32; 4]>(
+                context,
+                width,
+                height,
+                Interpolation::Nearest,
+                Interpolation::Nearest,
+                None,
+                Wrapping::ClampToEdge,
+                Wrapping::ClampToEdge,
+            )
+        };
+        Self {
+            position: channel(),
+            normal: channel(),
+            color: channel(),
+            orm: channel(),
+            uv: channel(),
+            depth_visual: channel(),
+            depth: DepthTexture2D::new::<f32>(
+                context,
+                width,
+                height,
+                Wrapping::ClampToEdge,
+                Wrapping::ClampToEdge,
+            ),
+            width,
+            height,
+        }
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        Viewport::new_at_origo(self.width, self.height)
+    }
+
+    /// The texture matching `material_type`, or `None` for `Forward`/`DebugChannels`
+    /// (those select a pipeline, not a channel — `DebugChannels` itself falls back to
+    /// `Color` at the call site in `main.rs`).
+    pub fn color_channel(&self, material_type: MaterialType) -> Option<&Texture2D> {
+        match material_type {
+            MaterialType::Position => Some(&self.position),
+            MaterialType::Normal => Some(&self.normal),
+            MaterialType::Color => Some(&self.color),
+            MaterialType::Depth => Some(&self.depth_visual),
+            MaterialType::Orm => Some(&self.orm),
+            MaterialType::Uv => Some(&self.uv),
+            MaterialType::Forward | MaterialType::DebugChannels => None,
+        }
+    }
+
+    /// Renders every model into the G-buffer. `color`+`depth` are always written with
+    /// the models' real materials and geometry, the same lit result the forward path
+    /// would produce. If `selected_channel` is one of `Position`/`Normal`/`Depth`/`Orm`/
+    /// `Uv`, that one channel is additionally re-rendered through [`encode_channel`] —
+    /// and only that one, since `Color` is already covered above and re-encoding all
+    /// five channels every frame regardless of which one is on screen would GPU-upload
+    /// four throwaway meshes nobody is looking at.
+    pub fn geometry_pass(
+        &self,
+        ctx: &Context,
+        camera: &Camera,
+        models: &mut [&mut ModelEntry],
+        lights: &[&dyn Light],
+        selected_channel: MaterialType,
+    ) {
+        let color_target = self.color.as_color_target(None);
+        let depth_target = self.depth.as_depth_target();
+        let target = RenderTarget::new(color_target, depth_target);
+        target.clear(ClearState::color_and_depth(0.0, 0.0, 0.0, 0.0, 1.0));
+        for model in models.iter_mut() {
+            model.render(&target, camera, lights);
+        }
+
+        let channel_texture = match selected_channel {
+            MaterialType::Position => Some(&self.position),
+            MaterialType::Normal => Some(&self.normal),
+            MaterialType::Depth => Some(&self.depth_visual),
+            MaterialType::Orm => Some(&self.orm),
+            MaterialType::Uv => Some(&self.uv),
+            MaterialType::Color | MaterialType::Forward | MaterialType::DebugChannels => None,
+        };
+        if let Some(texture) = channel_texture {
+            let channel_target = RenderTarget::new(texture.as_color_target(None), self.depth.as_depth_target());
+            channel_target.clear(ClearState::color_and_depth(0.0, 0.0, 0.0, 0.0, 1.0));
+            for model in models.iter() {
+                let encoded = encode_channel(ctx, camera, model, selected_channel);
+                channel_target.render(camera, &encoded, &[]);
+            }
+        }
+    }
+}
+
+/// Builds a throwaway copy of `model`'s geometry with per-vertex colors set to its world
+/// position, normal, depth, ORM triple or UV for `channel`, so the ordinary vertex-color
+/// interpolation a plain [`ColorMaterial`] already does reproduces per-pixel what a
+/// hand-written encode shader would — the same values [`GBuffer::geometry_pass`] would
+/// otherwise have to compute in a custom fragment shader.
+fn encode_channel(
+    ctx: &Context,
+    camera: &Camera,
+    model: &ModelEntry,
+    channel: MaterialType,
+) -> Gm<Mesh, ColorMaterial> {
+    let cpu_mesh = model.cpu_mesh();
+    let world = model.transform;
+
+    let mut encoded = CpuMesh {
+        positions: cpu_mesh.positions.clone(),
+        indices: cpu_mesh.indices.clone(),
+        ..Default::default()
+    };
+    encoded.colors = Some(match channel {
+        MaterialType::Position => {
+            let aabb_radius = model.world_aabb().size().magnitude().max(0.001) * 0.5;
+            let center = model.world_aabb().center();
+            cpu_mesh
+                .positions
+                .to_f32()
+                .iter()
+                .map(|&p| {
+                    let world_p = (world * p.extend(1.0)).truncate();
+                    encode_unit((world_p - center) / aabb_radius * 0.5 + vec3(0.5, 0.5, 0.5))
+                })
+                .collect()
+        }
+        MaterialType::Normal => {
+            let normal_mat = Mat3::from_cols(world.x.truncate(), world.y.truncate(), world.z.truncate());
+            cpu_mesh
+                .normals
+                .as_ref()
+                .expect("ModelEntry::new computes normals at load time")
+                .iter()
+                .map(|&n| encode_unit((normal_mat * n).normalize() * 0.5 + vec3(0.5, 0.5, 0.5)))
+                .collect()
+        }
+        MaterialType::Orm => {
+            let material = &model.normal_mesh.material;
+            let orm = vec3(1.0, material.roughness, material.metallic);
+            vec![encode_unit(orm); cpu_mesh.positions.to_f32().len()]
+        }
+        MaterialType::Uv => cpu_mesh
+            .uvs
+            .as_ref()
+            .map(|uvs| uvs.iter().map(|&uv| encode_unit(vec3(uv.x, uv.y, 0.0))).collect())
+            .unwrap_or_else(|| vec![Srgba::BLACK; cpu_mesh.positions.to_f32().len()]),
+        MaterialType::Depth => {
+            let view_proj = *camera.projection() * *camera.view();
+            cpu_mesh
+                .positions
+                .to_f32()
+                .iter()
+                .map(|&p| {
+                    let world_p = (world * p.extend(1.0)).truncate();
+                    let clip = view_proj * world_p.extend(1.0);
+                    let ndc_depth = if clip.w > 1e-5 { clip.z / clip.w } else { 1.0 };
+                    let depth_01 = ndc_depth.clamp(0.0, 1.0);
+                    encode_unit(vec3(depth_01, depth_01, depth_01))
+                })
+                .collect()
+        }
+        MaterialType::Color | MaterialType::Forward | MaterialType::DebugChannels => {
+            unreachable!("encode_channel is only called for the debug channel variants")
+        }
+    });
+
+    let mut mesh = Gm::new(Mesh::new(ctx, &encoded), ColorMaterial::default());
+    mesh.set_transformation(world);
+    mesh
+}
+
+/// Packs a `[0, 1]`-ish vector into an opaque vertex color, clamping out-of-range
+/// components (e.g. a position far outside the model's own bounding box) rather than
+/// wrapping, since a clipped debug view is more legible than one that's wrapped around.
+fn encode_unit(v: three_d::Vec3) -> Srgba {
+    let channel = |x: f32| (x.clamp(0.0, 1.0) * 255.0).round() as u8;
+    Srgba::new_opaque(channel(v.x), channel(v.y), channel(v.z))
+}
+
+/// Renders the chosen G-buffer channel full-screen, so debug modes can inspect an
+/// imported asset's normals, depth, UVs or ORM without leaving the Forward path.
+pub struct ChannelVisualizer {
+    quad: Gm<Mesh, ColorMaterial>,
+}
+
+impl ChannelVisualizer {
+    pub fn new(context: &Context) -> Self {
+        let mut quad_mesh = CpuMesh {
+            positions: Positions::F32(vec![
+                vec3(-1.0, -1.0, 0.0),
+                vec3(3.0, -1.0, 0.0),
+                vec3(-1.0, 3.0, 0.0),
+            ]),
+            uvs: Some(vec![vec2(0.0, 1.0), vec2(2.0, 1.0), vec2(0.0, -1.0)]),
+            indices: Indices::U32(vec![0, 1, 2]),
+            ..Default::default()
+        };
+        quad_mesh.compute_normals();
+        let quad = Gm::new(Mesh::new(context, &quad_mesh), ColorMaterial::default());
+        Self { quad }
+    }
+
+    pub fn show(&mut self, target: &RenderTarget, camera: &Camera, texture: &Texture2D) {
+        self.quad.material.texture = Some(Texture2DRef {
+            texture: std::sync::Arc::new(texture.clone()),
+            transformation: Mat3::from_scale(1.0),
+        });
+        self.quad.material.color = Srgba::WHITE;
+        // The quad's corners are screen-space coordinates, so draw it through a fixed
+        // orthographic camera spanning the viewport instead of the scene camera.
+        let viewport = camera.viewport();
+        let aspect = viewport.width as f32 / viewport.height.max(1) as f32;
+        let screen_camera = Camera::new_orthographic(
+            viewport,
+            vec3(0.0, 0.0, 1.0),
+            vec3(0.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+            2.0,
+            0.1,
+            10.0,
+        );
+        self.quad.set_transformation(Mat4::from_nonuniform_scale(aspect, 1.0, 1.0));
+        target.render(&screen_camera, &self.quad, &[]);
+    }
+}
@@ -0,0 +1,172 @@
+use anyhow::{anyhow, Result};
+use three_d::{vec3, Context, CpuMesh, Indices, Mat4, Positions, Quat, Vec3};
+
+use crate::ModelEntry;
+
+/// One node of an imported scene graph: a name, a local transform relative to its
+/// parent, the geometry attached at this node (if any), and its children.
+///
+/// `Resources` holds the roots of this tree instead of a flat model list so that
+/// multi-part imports (e.g. a helmet with a separate visor) keep their relative
+/// placement instead of being flattened to the origin.
+pub struct SceneNode {
+    pub name: String,
+    pub local_transform: Mat4,
+    pub model: Option<ModelEntry>,
+    pub children: Vec<SceneNode>,
+}
+
+impl SceneNode {
+    pub fn new(name: impl Into<String>, local_transform: Mat4) -> Self {
+        Self {
+            name: name.into(),
+            local_transform,
+            model: None,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn with_model(name: impl Into<String>, model: ModelEntry) -> Self {
+        Self {
+            model: Some(model),
+            ..Self::new(name, Mat4::from_scale(1.0))
+        }
+    }
+
+    /// Recomputes this node's world transform from `parent_world` and its own local
+    /// transform, pushes it into `model` (if any), then recurses into `children`.
+    pub fn update_world_transform(&mut self, parent_world: Mat4) {
+        let world = parent_world * self.local_transform;
+        if let Some(model) = &mut self.model {
+            model.set_transformation(world);
+        }
+        for child in &mut self.children {
+            child.update_world_transform(world);
+        }
+    }
+
+    /// Depth-first walk invoking `f` on every model in this subtree.
+    pub fn visit_models_mut(&mut self, f: &mut impl FnMut(&mut ModelEntry)) {
+        if let Some(model) = &mut self.model {
+            f(model);
+        }
+        for child in &mut self.children {
+            child.visit_models_mut(f);
+        }
+    }
+
+    /// Depth-first-collects mutable references to every model in this subtree, for
+    /// passes (culling, the debug-channel geometry pass) that need to hold them all at once.
+    pub fn models_mut(&mut self) -> Vec<&mut ModelEntry> {
+        let mut models = Vec::new();
+        if let Some(model) = &mut self.model {
+            models.push(model);
+        }
+        for child in &mut self.children {
+            models.extend(child.models_mut());
+        }
+        models
+    }
+
+    /// Looks up a descendant by path: an empty path is this node itself, otherwise the
+    /// first index selects a child and the rest of the path is resolved within it.
+    pub fn node_mut(&mut self, path: &[usize]) -> Option<&mut SceneNode> {
+        match path.split_first() {
+            None => Some(self),
+            Some((idx, rest)) => self.children.get_mut(*idx)?.node_mut(rest),
+        }
+    }
+}
+
+/// Loads a single asset file as scene graph roots: a glTF/glb file keeps its node
+/// hierarchy and local transforms, anything else becomes one root with identity transform.
+pub fn load_file(ctx: &Context, path: std::path::PathBuf) -> Result<Vec<SceneNode>> {
+    let is_gltf = matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("gltf") | Some("glb")
+    );
+    if is_gltf {
+        return load_gltf_scene(ctx, &path);
+    }
+
+    let name = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("Model")
+        .to_string();
+    let cpu_mesh: CpuMesh = three_d_asset::io::load(&[path]).unwrap().deserialize("")?;
+    let model = ModelEntry::new(ctx, cpu_mesh);
+    Ok(vec![SceneNode::with_model(name, model)])
+}
+
+fn load_gltf_scene(ctx: &Context, path: &std::path::Path) -> Result<Vec<SceneNode>> {
+    let (document, buffers, _images) = gltf::import(path)?;
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or_else(|| anyhow!("glTF file {:?} has no scenes", path))?;
+
+    Ok(scene
+        .nodes()
+        .map(|node| build_scene_node(ctx, &node, &buffers))
+        .collect())
+}
+
+fn build_scene_node(ctx: &Context, node: &gltf::Node, buffers: &[gltf::buffer::Data]) -> SceneNode {
+    let name = node
+        .name()
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("Node {}", node.index()));
+    let local_transform = node_local_transform(node);
+    let model = node_mesh(node, buffers).map(|cpu_mesh| ModelEntry::new(ctx, cpu_mesh));
+    let children = node
+        .children()
+        .map(|child| build_scene_node(ctx, &child, buffers))
+        .collect();
+
+    SceneNode {
+        name,
+        local_transform,
+        model,
+        children,
+    }
+}
+
+fn node_local_transform(node: &gltf::Node) -> Mat4 {
+    let (t, r, s) = node.transform().decomposed();
+    let translation = Mat4::from_translation(vec3(t[0], t[1], t[2]));
+    let rotation: Mat4 = Quat::new(r[3], r[0], r[1], r[2]).into();
+    let scale = Mat4::from_nonuniform_scale(s[0], s[1], s[2]);
+    translation * rotation * scale
+}
+
+fn node_mesh(node: &gltf::Node, buffers: &[gltf::buffer::Data]) -> Option<CpuMesh> {
+    let mesh = node.mesh()?;
+    // TODO: a mesh node can carry more than one primitive (e.g. per-material submeshes);
+    // only the first is read here and the rest are silently dropped. Fine for the
+    // single-primitive sample assets this loader has been tested against, but worth
+    // merging all primitives into one CpuMesh (or emitting a child SceneNode per extra
+    // primitive) before trusting this on arbitrary glTF.
+    let primitive = mesh.primitives().next()?;
+    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+
+    let positions: Vec<Vec3> = reader
+        .read_positions()?
+        .map(|p| vec3(p[0], p[1], p[2]))
+        .collect();
+    let indices: Vec<u32> = reader.read_indices()?.into_u32().collect();
+    let normals: Option<Vec<Vec3>> = reader
+        .read_normals()
+        .map(|it| it.map(|n| vec3(n[0], n[1], n[2])).collect());
+
+    let mut cpu_mesh = CpuMesh {
+        positions: Positions::F32(positions),
+        indices: Indices::U32(indices),
+        normals,
+        ..Default::default()
+    };
+    if cpu_mesh.normals.is_none() {
+        cpu_mesh.compute_normals();
+    }
+    Some(cpu_mesh)
+}
@@ -0,0 +1,426 @@
+//! Per-light shadow settings and the shadow map they drive, replacing the single
+//! hardcoded `generate_shadow_map(1024, ...)` call every light used to share.
+
+use three_d::{
+    radians, vec3, Blend, Camera, ClearState, Context, Cull, DepthTest, DepthTexture2D,
+    FragmentAttributes, InnerSpace, Light, Mat4, Material, MaterialType as GpuMaterialClass,
+    Program, RenderStates, RenderTarget, Vec3, Viewport, Wrapping,
+};
+
+use crate::ModelEntry;
+
+/// How a light's shadow map is filtered when [`ShadowMaterial`]'s fragment shader
+/// samples it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShadowFilterMode {
+    /// A single shadow-map sample, like the renderer's previous default.
+    None,
+    /// The Poisson-disc kernel at a fixed one-texel radius: cheap, slightly softened
+    /// edges. Samples are read with `textureLoad`; no hardware comparison filtering is used.
+    Hardware2x2,
+    /// Percentage-closer filtering: average several samples in a Poisson disc.
+    Pcf,
+    /// Percentage-closer soft shadows: a blocker search sizes the PCF kernel so
+    /// contact shadows stay crisp while distant ones soften.
+    Pcss,
+}
+
+impl ShadowFilterMode {
+    /// Numeric encoding handed to the fragment shader as a plain `i32` uniform, since
+    /// WGSL has no direct equivalent of a Rust enum.
+    fn as_shader_code(self) -> i32 {
+        match self {
+            ShadowFilterMode::None => 0,
+            ShadowFilterMode::Hardware2x2 => 1,
+            ShadowFilterMode::Pcf => 2,
+            ShadowFilterMode::Pcss => 3,
+        }
+    }
+}
+
+/// Per-light shadow settings, editable live from the GUI.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowConfig {
+    pub enabled: bool,
+    pub map_resolution: u32,
+    pub bias: f32,
+    pub filter: ShadowFilterMode,
+    /// World-space size of the light's emitting surface, used by PCSS to estimate
+    /// penumbra width. Ignored by every other filter mode.
+    pub light_size: f32,
+}
+
+impl Default for ShadowConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            map_resolution: 1024,
+            bias: 0.0001,
+            filter: ShadowFilterMode::Pcf,
+            light_size: 0.2,
+        }
+    }
+}
+
+/// A light-space depth buffer this module renders once per frame. Deliberately
+/// separate from three_d's own `generate_shadow_map`/built-in shadow term (still called
+/// alongside this in the main loop): that gives every light a correct, per-pixel hard
+/// shadow; [`ShadowMaterial`] samples this buffer directly on the GPU, per fragment,
+/// with `ShadowConfig::filter`'s kernel and `bias`, so the soft penumbra it computes
+/// actually reaches the rendered image instead of only ever gating whether a light
+/// reaches a model at all.
+pub struct ShadowMap {
+    depth: DepthTexture2D,
+    view_proj: Mat4,
+}
+
+impl ShadowMap {
+    pub fn new(ctx: &Context, size: u32) -> Self {
+        let depth = DepthTexture2D::new::<f32>(ctx, size, size, Wrapping::ClampToEdge, Wrapping::ClampToEdge);
+        Self {
+            depth,
+            view_proj: Mat4::from_scale(1.0),
+        }
+    }
+
+    /// A minimal placeholder map for a light whose shadows are disabled this frame: a
+    /// 1x1 depth texture that's never actually sampled (`build_shadow_map`'s caller only
+    /// includes enabled casters in the slice passed to [`ShadowMaterial`]), so a
+    /// disabled light skips both the real-resolution texture allocation and the
+    /// light-camera framing work that would otherwise run unconditionally every frame.
+    pub fn disabled(ctx: &Context) -> Self {
+        Self::new(ctx, 1)
+    }
+
+    /// Renders `casters` depth-only from `light_camera`'s point of view. Unlike the
+    /// per-vertex CPU occlusion this module used to compute, nothing is read back here —
+    /// [`ShadowMaterial`] samples `depth_texture()` directly on the GPU at draw time.
+    pub fn build(&mut self, light_camera: &Camera, casters: &mut [&mut ModelEntry]) {
+        self.view_proj = *light_camera.projection() * *light_camera.view();
+        let target = self.depth.as_depth_target();
+        target.clear(ClearState::depth(1.0));
+        for model in casters.iter_mut() {
+            target.render(light_camera, &model.normal_mesh, &[]);
+        }
+    }
+
+    pub fn depth_texture(&self) -> &DepthTexture2D {
+        &self.depth
+    }
+
+    pub fn view_proj(&self) -> Mat4 {
+        self.view_proj
+    }
+}
+
+/// One enabled light's shadow map, as consumed by [`ShadowMaterial`].
+#[derive(Clone, Copy)]
+pub struct ShadowCaster<'a> {
+    pub map: &'a ShadowMap,
+    pub config: ShadowConfig,
+}
+
+/// A fixed 4x4 Poisson disc, reused by the shader's `Hardware2x2`/`Pcf`/`Pcss` kernels so samples
+/// don't line up on texel-aligned rows and band the penumbra. Declared as a WGSL source
+/// fragment below rather than a Rust array, since it's only ever read on the GPU now.
+const POISSON_DISC_WGSL: &str = "
+const POISSON_DISC_16 = array<vec2<f32>, 16>(
+    vec2<f32>(-0.94201624, -0.39906216), vec2<f32>(0.94558609, -0.76890725),
+    vec2<f32>(-0.094184101, -0.92938870), vec2<f32>(0.34495938, 0.29387760),
+    vec2<f32>(-0.91588581, 0.45771432), vec2<f32>(-0.81544232, -0.87912464),
+    vec2<f32>(-0.38277543, 0.27676845), vec2<f32>(0.97484398, 0.75648379),
+    vec2<f32>(0.44323325, -0.97511554), vec2<f32>(0.53742981, -0.47373420),
+    vec2<f32>(-0.26496911, -0.41893023), vec2<f32>(0.79197514, 0.19090188),
+    vec2<f32>(-0.24188840, 0.99706507), vec2<f32>(-0.81409955, 0.91437590),
+    vec2<f32>(0.19984126, 0.78641367), vec2<f32>(0.14383161, -0.14100790),
+);
+";
+
+/// A material that draws no color of its own: its fragment shader samples up to 3
+/// casters' shadow maps with each one's `ShadowFilterMode` kernel and takes the darkest
+/// result, then outputs `(0, 0, 0, occlusion)`. Drawing `model`'s own geometry again
+/// with this material, alpha-blended on top of its already-lit draw, turns that
+/// per-fragment occlusion into a soft shadow edge in the final image — replacing the
+/// old approach of baking one occlusion sample per *vertex* into a throwaway CPU mesh,
+/// which capped the filter's resolution at the model's own vertex density and required
+/// reading the whole shadow depth buffer back to the CPU every frame. At most 3 casters
+/// are supported, matching the 3 fixed shadow-casting lights the main loop drives.
+pub struct ShadowMaterial<'a> {
+    casters: Vec<ShadowCaster<'a>>,
+}
+
+impl<'a> ShadowMaterial<'a> {
+    pub fn new(casters: Vec<ShadowCaster<'a>>) -> Self {
+        debug_assert!(casters.len() <= 3, "ShadowMaterial supports at most 3 casters");
+        Self { casters }
+    }
+
+    fn caster_uniforms(&self, slot: usize) -> (Mat4, f32, i32, f32, i32) {
+        match self.casters.get(slot) {
+            Some(caster) => (
+                caster.map.view_proj(),
+                caster.config.bias,
+                caster.config.filter.as_shader_code(),
+                caster.config.light_size,
+                1,
+            ),
+            None => (Mat4::from_scale(1.0), 0.0, 0, 0.0, 0),
+        }
+    }
+}
+
+impl<'a> Material for ShadowMaterial<'a> {
+    fn id(&self) -> u16 {
+        // Arbitrary, but distinct from any id three_d's own built-in materials use, so
+        // this material's compiled shader variant isn't confused with theirs.
+        0x5ad0
+    }
+
+    fn fragment_shader_source(&self, _lights: &[&dyn Light]) -> String {
+        // Bindings live in group 1: three_d's own per-draw camera/light uniforms occupy
+        // group 0, so material-specific bindings start at the next group to avoid
+        // colliding with those. Each caster's fields are flat top-level uniforms rather
+        // than one struct-typed uniform per caster, since `Program::use_uniform` binds
+        // by the name of a plain scalar/matrix/vector uniform, not a struct field path.
+        format!(
+            "
+            @group(1) @binding(0) var<uniform> caster0_view_proj: mat4x4<f32>;
+            @group(1) @binding(1) var<uniform> caster0_bias: f32;
+            @group(1) @binding(2) var<uniform> caster0_filter_mode: i32;
+            @group(1) @binding(3) var<uniform> caster0_light_size: f32;
+            @group(1) @binding(4) var<uniform> caster0_active: i32;
+            @group(1) @binding(5) var<uniform> caster1_view_proj: mat4x4<f32>;
+            @group(1) @binding(6) var<uniform> caster1_bias: f32;
+            @group(1) @binding(7) var<uniform> caster1_filter_mode: i32;
+            @group(1) @binding(8) var<uniform> caster1_light_size: f32;
+            @group(1) @binding(9) var<uniform> caster1_active: i32;
+            @group(1) @binding(10) var<uniform> caster2_view_proj: mat4x4<f32>;
+            @group(1) @binding(11) var<uniform> caster2_bias: f32;
+            @group(1) @binding(12) var<uniform> caster2_filter_mode: i32;
+            @group(1) @binding(13) var<uniform> caster2_light_size: f32;
+            @group(1) @binding(14) var<uniform> caster2_active: i32;
+            @group(1) @binding(15) var shadow_map0: texture_depth_2d;
+            @group(1) @binding(16) var shadow_map1: texture_depth_2d;
+            @group(1) @binding(17) var shadow_map2: texture_depth_2d;
+
+            {poisson}
+
+            fn shadow_sample(tex: texture_depth_2d, uv: vec2<f32>) -> f32 {{
+                let size = vec2<f32>(textureDimensions(tex));
+                let texel = vec2<i32>(clamp(uv * size, vec2<f32>(0.0), size - vec2<f32>(1.0)));
+                return textureLoad(tex, texel, 0);
+            }}
+
+            fn shadow_test(tex: texture_depth_2d, uv: vec2<f32>, frag_depth: f32, bias: f32) -> f32 {{
+                let stored = shadow_sample(tex, uv);
+                if (frag_depth - bias > stored) {{
+                    return 1.0;
+                }}
+                return 0.0;
+            }}
+
+            fn shadow_find_blockers(tex: texture_depth_2d, uv: vec2<f32>, frag_depth: f32, radius_texels: f32) -> vec2<f32> {{
+                var sum = 0.0;
+                var count = 0.0;
+                let size = vec2<f32>(textureDimensions(tex));
+                for (var i = 0; i < 16; i = i + 1) {{
+                    let offset = POISSON_DISC_16[i] * radius_texels / size;
+                    let depth = shadow_sample(tex, uv + offset);
+                    if (depth < frag_depth) {{
+                        sum = sum + depth;
+                        count = count + 1.0;
+                    }}
+                }}
+                if (count < 1.0) {{
+                    return vec2<f32>(0.0, 0.0);
+                }}
+                return vec2<f32>(sum / count, count);
+            }}
+
+            fn shadow_occlusion(
+                tex: texture_depth_2d,
+                view_proj: mat4x4<f32>,
+                bias: f32,
+                filter_mode: i32,
+                light_size: f32,
+                active: i32,
+                world_pos: vec3<f32>,
+            ) -> f32 {{
+                if (active == 0) {{
+                    return 0.0;
+                }}
+                let clip = view_proj * vec4<f32>(world_pos, 1.0);
+                if (clip.w <= 0.00001) {{
+                    return 0.0;
+                }}
+                let ndc = clip.xyz / clip.w;
+                if (ndc.x < -1.0 || ndc.x > 1.0 || ndc.y < -1.0 || ndc.y > 1.0) {{
+                    return 0.0;
+                }}
+                let uv = vec2<f32>(ndc.x * 0.5 + 0.5, 1.0 - (ndc.y * 0.5 + 0.5));
+                let frag_depth = ndc.z;
+                let size = vec2<f32>(textureDimensions(tex));
+
+                if (filter_mode == 0) {{
+                    return shadow_test(tex, uv, frag_depth, bias);
+                }}
+
+                var radius_texels = 1.0;
+                if (filter_mode == 1) {{
+                    radius_texels = 1.0;
+                }} else if (filter_mode == 2) {{
+                    radius_texels = 3.0;
+                }} else {{
+                    let blockers = shadow_find_blockers(tex, uv, frag_depth, 4.0);
+                    if (blockers.y < 1.0) {{
+                        return 0.0;
+                    }}
+                    let penumbra = (frag_depth - blockers.x) / max(blockers.x, 0.0001) * light_size;
+                    radius_texels = clamp(penumbra * size.x, 1.0, 8.0);
+                }}
+
+                var occluded = 0.0;
+                for (var i = 0; i < 16; i = i + 1) {{
+                    let offset = POISSON_DISC_16[i] * radius_texels / size;
+                    occluded = occluded + shadow_test(tex, uv + offset, frag_depth, bias);
+                }}
+                return occluded / 16.0;
+            }}
+
+            fn fragment_shader(in: VertexOutput) -> vec4<f32> {{
+                var occlusion = 0.0;
+                occlusion = max(occlusion, shadow_occlusion(
+                    shadow_map0, caster0_view_proj, caster0_bias, caster0_filter_mode, caster0_light_size, caster0_active, in.position,
+                ));
+                occlusion = max(occlusion, shadow_occlusion(
+                    shadow_map1, caster1_view_proj, caster1_bias, caster1_filter_mode, caster1_light_size, caster1_active, in.position,
+                ));
+                occlusion = max(occlusion, shadow_occlusion(
+                    shadow_map2, caster2_view_proj, caster2_bias, caster2_filter_mode, caster2_light_size, caster2_active, in.position,
+                ));
+                return vec4<f32>(0.0, 0.0, 0.0, clamp(occlusion, 0.0, 1.0));
+            }}
+            ",
+            poisson = POISSON_DISC_WGSL,
+        )
+    }
+
+    fn fragment_attributes(&self) -> FragmentAttributes {
+        FragmentAttributes {
+            position: true,
+            ..FragmentAttributes::NONE
+        }
+    }
+
+    fn use_uniforms(&self, program: &Program, _camera: &Camera, _lights: &[&dyn Light]) {
+        for (slot, prefix) in ["caster0", "caster1", "caster2"].into_iter().enumerate() {
+            let (view_proj, bias, filter_mode, light_size, active) = self.caster_uniforms(slot);
+            program.use_uniform(&format!("{prefix}_view_proj"), view_proj);
+            program.use_uniform(&format!("{prefix}_bias"), bias);
+            program.use_uniform(&format!("{prefix}_filter_mode"), filter_mode);
+            program.use_uniform(&format!("{prefix}_light_size"), light_size);
+            program.use_uniform(&format!("{prefix}_active"), active);
+        }
+        for (slot, name) in ["shadow_map0", "shadow_map1", "shadow_map2"].into_iter().enumerate() {
+            if let Some(caster) = self.casters.get(slot) {
+                program.use_depth_texture(name, caster.map.depth_texture());
+            }
+        }
+    }
+
+    fn render_states(&self) -> RenderStates {
+        RenderStates {
+            blend: Blend::TRANSPARENCY,
+            cull: Cull::Back,
+            depth_test: DepthTest::LessOrEqual,
+            ..Default::default()
+        }
+    }
+
+    fn material_type(&self) -> GpuMaterialClass {
+        GpuMaterialClass::Transparent
+    }
+}
+
+/// Draws `model`'s own geometry a second time, alpha-blended on top of its already-lit
+/// draw, sampling each of `casters`' shadow maps per fragment through [`ShadowMaterial`].
+/// Does nothing if `casters` is empty, so a scene with every light's shadows disabled
+/// pays nothing for this pass beyond the check itself.
+pub fn draw_shadow_overlay(
+    target: &RenderTarget,
+    camera: &Camera,
+    model: &ModelEntry,
+    casters: Vec<ShadowCaster>,
+) {
+    if casters.is_empty() {
+        return;
+    }
+    let material = ShadowMaterial::new(casters);
+    target.render_with_material(camera, model.gpu_mesh(), &material, &[]);
+}
+
+/// Frames an orthographic camera around `casters`' combined world-space bounding sphere,
+/// looking along `direction`, for a directional light's shadow map.
+pub fn directional_shadow_camera(
+    viewport: Viewport,
+    direction: Vec3,
+    casters: &[&mut ModelEntry],
+) -> Camera {
+    let (center, radius) = bounding_sphere(casters);
+    let eye = center - direction.normalize() * radius * 2.0;
+    Camera::new_orthographic(
+        viewport,
+        eye,
+        center,
+        vec3(0.0, 1.0, 0.0),
+        radius * 2.0,
+        0.01,
+        radius * 4.0,
+    )
+}
+
+/// Frames a perspective camera at `position`, looking along `direction` with
+/// `cone_angle_radians` (the spot light's own cone angle) as its half-angle field of
+/// view, for a spot light's shadow map.
+pub fn spot_shadow_camera(
+    viewport: Viewport,
+    position: Vec3,
+    direction: Vec3,
+    cone_angle_radians: f32,
+    casters: &[&mut ModelEntry],
+) -> Camera {
+    let (center, radius) = bounding_sphere(casters);
+    let far = (center - position).magnitude() + radius * 2.0;
+    Camera::new_perspective(
+        viewport,
+        position,
+        position + direction.normalize(),
+        vec3(0.0, 1.0, 0.0),
+        radians(cone_angle_radians * 2.0),
+        0.01,
+        far.max(0.1),
+    )
+}
+
+fn bounding_sphere(models: &[&mut ModelEntry]) -> (Vec3, f32) {
+    let mut min = vec3(f32::MAX, f32::MAX, f32::MAX);
+    let mut max = vec3(f32::MIN, f32::MIN, f32::MIN);
+    let mut any = false;
+    for model in models {
+        any = true;
+        let aabb = model.world_aabb();
+        let (aabb_min, aabb_max) = (aabb.min(), aabb.max());
+        min.x = min.x.min(aabb_min.x);
+        min.y = min.y.min(aabb_min.y);
+        min.z = min.z.min(aabb_min.z);
+        max.x = max.x.max(aabb_max.x);
+        max.y = max.y.max(aabb_max.y);
+        max.z = max.z.max(aabb_max.z);
+    }
+    if !any {
+        return (vec3(0.0, 0.0, 0.0), 1.0);
+    }
+    let center = (min + max) * 0.5;
+    let radius = (max - min).magnitude().max(1.0) * 0.5;
+    (center, radius)
+}